@@ -0,0 +1,276 @@
+//! A multiplexing client that owns the i3 connection in a background task, so callers don't
+//! have to pass a single `UnixStream` around by hand like [`get`](../get/index.html) does.
+use std::io;
+
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_uds::UnixStream;
+
+use i3ipc_types::{
+    event::Event,
+    msg::{Msg, Subscribe},
+    reply, MsgResponse,
+};
+
+use crate::io as i3io;
+
+/// Capacity of the `broadcast` channel events are published on.
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+/// Capacity of the `mpsc` channel requests are submitted on.
+const REQUEST_CHANNEL_CAPACITY: usize = 32;
+
+/// A request submitted to the background actor: the message to send, its optional payload, and
+/// the `oneshot` sender the actor replies on once it has read the matching response.
+struct Request {
+    msg: Msg,
+    payload: Option<String>,
+    reply: oneshot::Sender<io::Result<Vec<u8>>>,
+}
+
+/// A cheap, `Clone`-able handle to a connection to i3, managed by a background task.
+///
+/// Clone it freely to share a single connection across tasks; requests from every clone are
+/// serialized onto the connection by the background actor and answered in the order i3 replies.
+/// Dropping every clone closes the connection and stops the actor.
+#[derive(Clone)]
+pub struct I3Client {
+    requests: mpsc::Sender<Request>,
+    events: broadcast::Sender<Event>,
+}
+
+impl I3Client {
+    /// Take ownership of `stream` and spawn the background actor that drives it.
+    pub fn new(stream: UnixStream) -> Self {
+        let (request_tx, request_rx) = mpsc::channel(REQUEST_CHANNEL_CAPACITY);
+        let (event_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        tokio::spawn(run(stream, request_rx, event_tx.clone()));
+
+        I3Client {
+            requests: request_tx,
+            events: event_tx,
+        }
+    }
+
+    /// Subscribe this client's connection to `events`. Until this is called, [`events`](#method.events)
+    /// never yields anything, since i3 only emits event frames once asked.
+    pub async fn subscribe(&self, events: &[Subscribe]) -> io::Result<()> {
+        let payload = serde_json::to_string(events)?;
+        let resp: MsgResponse<reply::Success> =
+            self.request(Msg::Subscribe, Some(payload)).await?;
+        i3io::check_subscribe_reply(&resp)?;
+        Ok(())
+    }
+
+    /// Subscribe to this client's stream of i3 events. Each call returns an independent
+    /// `broadcast::Receiver`, so multiple tasks can listen for events concurrently. Call
+    /// [`subscribe`](#method.subscribe) first, or nothing will ever arrive here.
+    pub fn events(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Run an arbitrary command on i3. Response is a `Vec` of success true/false.
+    pub async fn run_command<S: AsRef<str>>(
+        &self,
+        command: S,
+    ) -> io::Result<MsgResponse<Vec<reply::Success>>> {
+        self.request(Msg::RunCommand, Some(command.as_ref().to_string()))
+            .await
+    }
+
+    /// Get the current [`Workspaces`](reply::Workspaces).
+    pub async fn get_workspaces(&self) -> io::Result<MsgResponse<reply::Workspaces>> {
+        self.request(Msg::Workspaces, None).await
+    }
+
+    /// Get the complete [`Node`](reply::Node) tree.
+    pub async fn get_tree(&self) -> io::Result<MsgResponse<reply::Node>> {
+        self.request(Msg::Tree, None).await
+    }
+
+    /// Get all [`Outputs`](reply::Outputs).
+    pub async fn get_outputs(&self) -> io::Result<MsgResponse<reply::Outputs>> {
+        self.request(Msg::Outputs, None).await
+    }
+
+    /// Get all [`Marks`](reply::Marks).
+    pub async fn get_marks(&self) -> io::Result<MsgResponse<reply::Marks>> {
+        self.request(Msg::Marks, None).await
+    }
+
+    /// Get all [`BarIds`](reply::BarIds).
+    pub async fn get_bar_ids(&self) -> io::Result<MsgResponse<reply::BarIds>> {
+        self.request(Msg::BarConfig, None).await
+    }
+
+    /// Send a `Msg::Tick`, getting back [`Success`](reply::Success).
+    pub async fn get_tick(&self) -> io::Result<MsgResponse<reply::Success>> {
+        self.request(Msg::Tick, None).await
+    }
+
+    /// Send a `Msg::Sync`, getting back [`Success`](reply::Success).
+    pub async fn get_sync(&self) -> io::Result<MsgResponse<reply::Success>> {
+        self.request(Msg::Sync, None).await
+    }
+
+    /// Get the current [`Config`](reply::Config).
+    pub async fn get_config(&self) -> io::Result<MsgResponse<reply::Config>> {
+        self.request(Msg::Config, None).await
+    }
+
+    /// Get the [`BindingModes`](reply::BindingModes).
+    pub async fn get_binding_modes(&self) -> io::Result<MsgResponse<reply::BindingModes>> {
+        self.request(Msg::BindingModes, None).await
+    }
+
+    /// Submit a request to the background actor and wait for its decoded reply.
+    async fn request<T>(&self, msg: Msg, payload: Option<String>) -> io::Result<MsgResponse<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.requests
+            .send(Request {
+                msg,
+                payload,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "i3 client actor is gone"))?;
+        let bytes = reply_rx.await.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "i3 client actor dropped the request",
+            )
+        })??;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+/// Drive `stream` for as long as any `I3Client` handle is alive: write queued requests, match
+/// their replies back in FIFO order, and publish event frames on `events`.
+///
+/// This is two tasks, not one `select!` racing both halves of the connection every iteration:
+/// [`i3io::read_frame`] is built on `read_exact`, which isn't cancellation-safe, so racing it
+/// against `requests.recv()` on every loop tick would drop already-consumed bytes (and desync
+/// the frame boundary forever) the instant a request arrived mid-read. Instead [`write_loop`]
+/// drains `requests` on its own task and signals `shutdown` once it's done (every `I3Client`
+/// handle dropped); [`read_loop`] only ever races `read_frame` against that one-shot signal,
+/// which fires at most once, after there's nothing left worth reading anyway.
+async fn run(stream: UnixStream, requests: mpsc::Receiver<Request>, events: broadcast::Sender<Event>) {
+    let (read_half, write_half) = stream.into_split();
+    let (pending_tx, pending_rx) = mpsc::unbounded_channel();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+
+    let write_task = tokio::spawn(async move {
+        write_loop(write_half, requests, pending_tx).await;
+        let _ = shutdown_tx.send(());
+    });
+
+    read_loop(read_half, pending_rx, events, shutdown_rx).await;
+    let _ = write_task.await;
+}
+
+/// Drain `requests`, writing each one to `write_half` in turn and handing its reply sender off
+/// to [`read_loop`] (via `pending`) so it can be matched to the corresponding response.
+async fn write_loop(
+    mut write_half: OwnedWriteHalf,
+    mut requests: mpsc::Receiver<Request>,
+    pending: mpsc::UnboundedSender<oneshot::Sender<io::Result<Vec<u8>>>>,
+) {
+    while let Some(req) = requests.recv().await {
+        let payload = req.payload.as_deref().map(str::as_bytes);
+        if let Err(e) = i3io::write_frame(&mut write_half, req.msg, payload).await {
+            let _ = req.reply.send(Err(e));
+            continue;
+        }
+        if pending.send(req.reply).is_err() {
+            break;
+        }
+    }
+}
+
+/// Read frames off `read_half` for as long as the connection lasts, routing events to `events`
+/// and replies to the next sender queued in `pending`, in FIFO order. Returns once `read_frame`
+/// errors, `pending` closes, or `shutdown` fires.
+async fn read_loop(
+    mut read_half: OwnedReadHalf,
+    mut pending: mpsc::UnboundedReceiver<oneshot::Sender<io::Result<Vec<u8>>>>,
+    events: broadcast::Sender<Event>,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    loop {
+        let frame = tokio::select! {
+            frame = i3io::read_frame(&mut read_half) => frame,
+            _ = &mut shutdown => return,
+        };
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+        if frame.kind & i3io::EVENT_BIT != 0 {
+            if let Ok(event) = serde_json::from_slice::<Event>(&frame.payload) {
+                let _ = events.send(event);
+            }
+            continue;
+        }
+        match pending.recv().await {
+            Some(reply) => {
+                let _ = reply.send(Ok(frame.payload));
+            }
+            None => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand in for i3 on the other end of the pair: ack the subscribe, emit one event frame
+    /// (which should never be mistaken for a reply), then answer a `run_command` request.
+    async fn fake_i3(mut server: UnixStream) {
+        i3io::read_frame(&mut server).await.unwrap();
+        i3io::write_frame_raw(&mut server, Msg::Subscribe as u32, Some(br#"{"success":true}"#))
+            .await
+            .unwrap();
+
+        i3io::write_frame_raw(
+            &mut server,
+            i3io::EVENT_BIT | 6,
+            Some(br#"{"change":"restart"}"#),
+        )
+        .await
+        .unwrap();
+
+        let frame = i3io::read_frame(&mut server).await.unwrap();
+        assert_eq!(frame.kind, Msg::RunCommand as u32);
+        i3io::write_frame_raw(
+            &mut server,
+            Msg::RunCommand as u32,
+            Some(br#"[{"success":true}]"#),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn replies_land_on_the_right_caller_and_events_stay_off_the_reply_queue() {
+        let (client_stream, server) = UnixStream::pair().unwrap();
+        let client = I3Client::new(client_stream);
+        let mut events = client.events();
+        let server_task = tokio::spawn(fake_i3(server));
+
+        client.subscribe(&[]).await.unwrap();
+
+        // Reading this reply requires the actor to have already read past the interleaved event
+        // frame without handing it to the subscribe or run_command oneshot.
+        let run = client.run_command("exec true").await.unwrap();
+        assert!(run.body[0].success);
+
+        // The event ended up on the broadcast channel instead of being lost to a reply queue.
+        events.recv().await.unwrap();
+
+        server_task.await.unwrap();
+    }
+}
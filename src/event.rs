@@ -0,0 +1,81 @@
+//! Subscribing to i3 events as an async [`Stream`](futures::Stream), driven with
+//! `tokio-stream`'s `StreamExt::next` instead of a manual `read_msg_and` loop.
+use std::io;
+
+use async_stream::try_stream;
+use futures::Stream;
+use tokio_uds::UnixStream;
+
+use i3ipc_types::{
+    event::Event,
+    msg::{Msg, Subscribe},
+    reply,
+};
+
+use crate::io as i3io;
+
+/// Send `Msg::Subscribe` for `events` on `stream`, then return a `Stream` yielding decoded
+/// [`Event`]s for as long as it's polled.
+pub async fn subscribe(
+    stream: UnixStream,
+    events: &[Subscribe],
+) -> io::Result<impl Stream<Item = io::Result<Event>>> {
+    let stream = i3io::write_msg_json(stream, Msg::Subscribe, events)
+        .expect("serialization of Subscribe failed")
+        .await?;
+    let (stream, resp) = i3io::read_msg_and::<reply::Success>(stream).await?;
+    i3io::check_subscribe_reply(&resp)?;
+
+    Ok(try_stream! {
+        let mut stream = stream;
+        loop {
+            let (s, event) = i3io::read_msg::<Event>(stream).await?;
+            stream = s;
+            yield event;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    /// Stand in for i3: read the `Subscribe` request and answer it with `success`, then, only if
+    /// that was `true`, push one event frame.
+    async fn fake_i3(mut server: UnixStream, success: bool) {
+        i3io::read_frame(&mut server).await.unwrap();
+        let body = format!(r#"{{"success":{}}}"#, success);
+        i3io::write_frame_raw(&mut server, Msg::Subscribe as u32, Some(body.as_bytes()))
+            .await
+            .unwrap();
+        if success {
+            i3io::write_frame_raw(
+                &mut server,
+                i3io::EVENT_BIT | 6,
+                Some(br#"{"change":"restart"}"#),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_streams_decoded_events_after_a_successful_handshake() {
+        let (client, server) = UnixStream::pair().unwrap();
+        tokio::spawn(fake_i3(server, true));
+
+        let stream = subscribe(client, &[]).await.unwrap();
+        tokio::pin!(stream);
+        stream.next().await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn subscribe_rejects_a_failed_handshake() {
+        let (client, server) = UnixStream::pair().unwrap();
+        tokio::spawn(fake_i3(server, false));
+
+        let err = subscribe(client, &[]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+}
@@ -2,10 +2,12 @@
 //! and return a `Future` that will produce some data. They are mappings of `send_msg` and `read_msg_and`
 //! their appropriate output.
 //!
-//! While the protocol technically can work over any `AsyncRead`+`AsyncWrite`, in reality it's only
-//! implemented for `UnixStream`. So all the types are monomorphized here. However, if you need raw access
-//! use [send_msg](../io/fn.send_msg.html), [write_msg](../io/fn.write_msg.html), or
-//!  [write_msg_json](../io/fn.write_msg_json.html) (sends json payload along with message).
+//! The [`io`](../io/index.html) functions these are built on are generic over any
+//! `T: AsyncRead + AsyncWrite + Unpin`, but the wrappers here stay monomorphized to `UnixStream`
+//! for backward compatibility, since that's the transport i3 and Sway actually speak. If you need
+//! raw access, or a different transport, use [send_msg](../io/fn.send_msg.html),
+//! [write_msg](../io/fn.write_msg.html), or [write_msg_json](../io/fn.write_msg_json.html) (sends
+//! json payload along with message) directly.
 use futures::Future;
 use tokio_uds::UnixStream;
 
@@ -14,18 +16,19 @@ use i3ipc_types::{msg::Msg, reply, MsgResponse};
 
 use std::io;
 
-/// Run an arbitrary command for i3 and decode the responses, represented as vector of success true/false
-pub fn connect_and_run_command<S>(
+/// Run an arbitrary command for i3 (or Sway) and decode the responses, represented as vector of
+/// success true/false. Uses [`I3::connect_any`](../struct.I3.html#method.connect_any), so this
+/// works whether or not `$I3SOCK` is set.
+pub async fn connect_and_run_command<S>(
     command: S,
-) -> impl Future<Output = io::Result<MsgResponse<Vec<reply::Success>>>>
+) -> io::Result<MsgResponse<Vec<reply::Success>>>
 where
     S: AsRef<str>,
 {
-    I3::connect()
-        .expect("unable to get socket")
-        .and_then(|stream| i3io::write_msg(stream, msg::Msg::RunCommand, command))
-        .and_then(i3io::read_msg_and)
-        .map(|(_stream, resp)| resp)
+    let stream = I3::connect_any().await?;
+    let stream = i3io::write_msg(stream, msg::Msg::RunCommand, command).await?;
+    let (_stream, resp) = i3io::read_msg_and(stream).await?;
+    Ok(resp)
 }
 
 /// Run an arbitrary command on i3. Response is a `Vec` of success true/false.
@@ -91,6 +94,24 @@ pub fn get_binding_modes(
     i3io::send_msg(stream, Msg::BindingModes).and_then(i3io::read_msg_and)
 }
 
+/// i3 IPC message type 12, `GET_BINDING_STATE`. Not yet exposed as a `Msg` variant in
+/// `i3ipc_types`, so this is sent by raw type via [send_msg_raw](../io/fn.send_msg_raw.html).
+const GET_BINDING_STATE: u32 = 12;
+
+/// Body of the `GET_BINDING_STATE` reply: the name of the currently active binding mode. Not yet
+/// exposed in `i3ipc_types::reply`.
+#[derive(Debug, serde::Deserialize)]
+pub struct BindingState {
+    pub name: String,
+}
+
+/// Future to get the currently active [BindingState], sends `GET_BINDING_STATE`
+pub fn get_binding_state(
+    stream: UnixStream,
+) -> impl Future<Output = io::Result<(UnixStream, MsgResponse<BindingState>)>> {
+    i3io::send_msg_raw(stream, GET_BINDING_STATE).and_then(i3io::read_msg_and)
+}
+
 /// Future for [Config](../reply/struct.Config.html), sends [Config](../msg/enum.Msg.html#variant.Config)
 pub fn get_config(
     stream: UnixStream,
@@ -0,0 +1,192 @@
+//! Low-level functions for reading and writing i3 IPC messages.
+//!
+//! These are the building blocks the [`get`](../get/index.html) module's convenience functions
+//! are written in terms of. Reach for these directly if you need to send a message type that
+//! doesn't have a dedicated wrapper yet.
+//!
+//! Generic over `T: AsyncRead + AsyncWrite + Unpin` rather than hard-coded to `UnixStream`.
+use std::io;
+
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use i3ipc_types::{msg::Msg, reply, MsgResponse};
+
+/// Magic string that prefixes every i3 IPC message.
+const MAGIC: &[u8] = b"i3-ipc";
+
+/// The bit i3 sets on a frame's message-type field to mark it as an event notification rather
+/// than a reply to a request. See [`read_frame`].
+pub const EVENT_BIT: u32 = 1 << 31;
+
+/// A raw, not-yet-decoded i3 IPC frame: its message/event type tag and payload bytes.
+pub struct Frame {
+    pub kind: u32,
+    pub payload: Vec<u8>,
+}
+
+/// Write a message with no payload to `stream`.
+pub async fn send_msg<Io>(mut stream: Io, msg: Msg) -> io::Result<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    write_frame(&mut stream, msg, None).await?;
+    Ok(stream)
+}
+
+/// Write a message with no payload to `stream`, by raw numeric message type rather than a
+/// `Msg` variant. For message types not yet exposed on `i3ipc_types::msg::Msg`.
+pub async fn send_msg_raw<Io>(mut stream: Io, msg_type: u32) -> io::Result<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    write_frame_raw(&mut stream, msg_type, None).await?;
+    Ok(stream)
+}
+
+/// Write a message with a string payload to `stream`.
+pub async fn write_msg<Io, S>(mut stream: Io, msg: Msg, payload: S) -> io::Result<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+    S: AsRef<str>,
+{
+    write_frame(&mut stream, msg, Some(payload.as_ref().as_bytes())).await?;
+    Ok(stream)
+}
+
+/// Write a message with a JSON-encoded payload to `stream`.
+pub fn write_msg_json<Io, T>(
+    stream: Io,
+    msg: Msg,
+    payload: T,
+) -> serde_json::Result<impl std::future::Future<Output = io::Result<Io>>>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let payload = serde_json::to_string(&payload)?;
+    Ok(write_msg(stream, msg, payload))
+}
+
+/// Read a message header and payload from `stream` and decode it as a `MsgResponse<T>`.
+pub async fn read_msg_and<Io, T>(stream: Io) -> io::Result<(Io, MsgResponse<T>)>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+    T: serde::de::DeserializeOwned,
+{
+    read_msg(stream).await
+}
+
+/// Read a message header and payload from `stream` and decode the payload directly as `T`,
+/// without the `MsgResponse` wrapper. Event payloads (as opposed to command replies) aren't
+/// wrapped, so [`event::subscribe`](../event/fn.subscribe.html) reads through this directly.
+pub async fn read_msg<Io, T>(mut stream: Io) -> io::Result<(Io, T)>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+    T: serde::de::DeserializeOwned,
+{
+    let frame = read_frame(&mut stream).await?;
+    let decoded = serde_json::from_slice(&frame.payload)?;
+    Ok((stream, decoded))
+}
+
+/// Check the reply to a `Msg::Subscribe` request, turning a `{"success": false}` body into an
+/// `io::Error`. Shared by [`event::subscribe`](../event/fn.subscribe.html) and
+/// [`I3Client::subscribe`](../client/struct.I3Client.html#method.subscribe) so the two entry
+/// points can't drift on the rejection message.
+pub fn check_subscribe_reply(resp: &MsgResponse<reply::Success>) -> io::Result<()> {
+    if !resp.body.success {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "i3 rejected the subscribe request",
+        ));
+    }
+    Ok(())
+}
+
+/// Write a frame (header plus optional payload) to `writer`.
+///
+/// This is the half of the wire format that doesn't need to hand the stream back afterwards, so
+/// it takes `writer` by reference. [`I3Client`](../client/struct.I3Client.html) uses it directly
+/// against a split write half, while [`send_msg`]/[`write_msg`] wrap it for the by-value API the
+/// rest of this module exposes.
+pub async fn write_frame<W>(writer: &mut W, msg: Msg, payload: Option<&[u8]>) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    write_frame_raw(writer, msg as u32, payload).await
+}
+
+/// Write a frame by raw numeric message type rather than a `Msg` variant. See [`send_msg_raw`].
+pub async fn write_frame_raw<W>(
+    writer: &mut W,
+    msg_type: u32,
+    payload: Option<&[u8]>,
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let len = payload.map(<[u8]>::len).unwrap_or(0) as u32;
+    writer.write_all(&header(msg_type, len)).await?;
+    if let Some(payload) = payload {
+        writer.write_all(payload).await?;
+    }
+    Ok(())
+}
+
+/// Read one raw frame (header plus payload) from `reader`.
+///
+/// `Frame::kind` is the message type i3 sent it as; check it against [`EVENT_BIT`] to tell an
+/// event notification apart from a reply to a request.
+pub async fn read_frame<R>(reader: &mut R) -> io::Result<Frame>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut header = [0u8; 14];
+    reader.read_exact(&mut header).await?;
+    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    Ok(Frame { kind, payload })
+}
+
+/// Build the 14-byte `i3-ipc` header: magic string, payload length, and message type.
+fn header(msg_type: u32, len: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(MAGIC.len() + 8);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&len.to_ne_bytes());
+    header.extend_from_slice(&msg_type.to_ne_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::duplex;
+
+    #[tokio::test]
+    async fn write_frame_round_trips_through_read_frame() {
+        let (mut client, mut server) = duplex(64);
+        write_frame(&mut client, Msg::RunCommand, Some(b"exec foo"))
+            .await
+            .unwrap();
+
+        let frame = read_frame(&mut server).await.unwrap();
+
+        assert_eq!(frame.kind, Msg::RunCommand as u32);
+        assert_eq!(frame.payload, b"exec foo");
+    }
+
+    #[tokio::test]
+    async fn read_frame_sees_the_event_bit() {
+        let (mut client, mut server) = duplex(64);
+        write_frame_raw(&mut client, Msg::Workspaces as u32 | EVENT_BIT, None)
+            .await
+            .unwrap();
+
+        let frame = read_frame(&mut server).await.unwrap();
+
+        assert_ne!(frame.kind & EVENT_BIT, 0);
+    }
+}
@@ -0,0 +1,77 @@
+//! `tokio-i3ipc` is an implementation of the [i3 ipc
+//! protocol](https://i3wm.org/docs/ipc.html) for [`tokio`](https://docs.rs/tokio).
+//!
+//! Typically you'll want to start with [`I3::connect`](struct.I3.html#method.connect), which
+//! opens a `UnixStream` to i3's socket, and then use the functions in the [`get`](get/index.html)
+//! module to exchange messages over it.
+use std::env;
+use std::io;
+
+use tokio::process::Command;
+use tokio_uds::UnixStream;
+
+pub mod client;
+pub mod event;
+pub mod get;
+pub mod io;
+
+pub use client::I3Client;
+
+pub use i3ipc_types::{msg, reply, MsgResponse};
+
+/// The environment variable i3 sets to point at its IPC socket.
+const I3_SOCKET_ENV: &str = "I3SOCK";
+/// The environment variable Sway sets to point at its IPC socket.
+const SWAY_SOCKET_ENV: &str = "SWAYSOCK";
+
+/// Entry point for connecting to i3. It holds no state: it only locates and opens the socket;
+/// the actual message exchange happens via the functions in [`get`](get/index.html).
+pub struct I3;
+
+impl I3 {
+    /// Connect to the i3 IPC socket pointed to by the `I3SOCK` environment variable.
+    pub fn connect() -> io::Result<impl std::future::Future<Output = io::Result<UnixStream>>> {
+        let path = env::var(I3_SOCKET_ENV)
+            .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "I3SOCK not set"))?;
+        Ok(UnixStream::connect(path))
+    }
+
+    /// Connect to i3 or a Sway-compatible compositor, without requiring `I3SOCK` to be set.
+    pub async fn connect_any() -> io::Result<UnixStream> {
+        let path = socket_path().await?;
+        UnixStream::connect(path).await
+    }
+}
+
+/// Resolve the path to the i3/Sway IPC socket.
+///
+/// Tries, in order: `$I3SOCK`, `$SWAYSOCK`, `i3 --get-socketpath`, `sway --get-socketpath`.
+/// Returns an `io::Error` if none of these produce a usable path.
+async fn socket_path() -> io::Result<String> {
+    if let Ok(path) = env::var(I3_SOCKET_ENV) {
+        if !path.is_empty() {
+            return Ok(path);
+        }
+    }
+    if let Ok(path) = env::var(SWAY_SOCKET_ENV) {
+        if !path.is_empty() {
+            return Ok(path);
+        }
+    }
+    for program in &["i3", "sway"] {
+        if let Ok(output) = Command::new(program).arg("--get-socketpath").output().await {
+            if output.status.success() {
+                let path = String::from_utf8_lossy(&output.stdout)
+                    .trim_end_matches('\n')
+                    .to_string();
+                if !path.is_empty() {
+                    return Ok(path);
+                }
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "could not find i3/sway socket: $I3SOCK, $SWAYSOCK, `i3 --get-socketpath`, and `sway --get-socketpath` all failed",
+    ))
+}